@@ -0,0 +1,163 @@
+//! User-prompted input variables, referenced from a [`Definition`](crate::Definition) as
+//! `${input:ID}`, mirroring VS Code's `inputs` array.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A single user-facing input, resolved once per task run and substituted into any
+/// `${input:ID}` reference in the task's `command`, `args`, `cwd`, `label`, or `env`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TaskInput {
+    /// Unique (within the task file) id of this input, referenced as `${input:ID}`.
+    pub id: String,
+    /// Message shown to the user when asking for this input's value.
+    pub description: String,
+    /// How the value should be obtained.
+    #[serde(flatten)]
+    pub kind: TaskInputKind,
+}
+
+/// The way a [`TaskInput`]'s value is obtained.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TaskInputKind {
+    /// Free-form text, entered in a textbox.
+    PromptString {
+        /// Value to pre-fill the textbox with.
+        #[serde(default)]
+        default: Option<String>,
+    },
+    /// A choice from a fixed list of options, entered via a picker.
+    PickString {
+        /// The options offered to the user.
+        options: Vec<String>,
+    },
+    /// The trimmed stdout of a shell command, run without further user interaction.
+    Command {
+        /// The command to run.
+        command: String,
+        /// Arguments to the command.
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// Resolves [`TaskInput`]s to concrete values, e.g. by showing a UI picker/textbox or running
+/// the input's `command`. Implemented by the layer that owns the UI (or, in tests, a fake).
+pub trait InputResolver {
+    /// Resolves `input`'s value. Returns `None` if the user cancelled the prompt (or the
+    /// `command` kind failed to run), in which case the task referencing it should be omitted.
+    fn resolve(&self, input: &TaskInput) -> Option<String>;
+}
+
+/// Extracts every `${input:ID}` id referenced in `text`, in order of appearance.
+pub(crate) fn referenced_input_ids(text: &str) -> Vec<String> {
+    const PREFIX: &str = "${input:";
+    let mut ids = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PREFIX) {
+        let after_prefix = &rest[start + PREFIX.len()..];
+        let Some(end) = after_prefix.find('}') else {
+            break;
+        };
+        ids.push(after_prefix[..end].to_string());
+        rest = &after_prefix[end + 1..];
+    }
+    ids
+}
+
+/// Replaces every `${input:ID}` reference in `text` with its resolved value from `values`.
+/// References with no entry in `values` are left untouched.
+pub(crate) fn substitute_inputs(
+    text: &str,
+    values: &collections::HashMap<String, String>,
+) -> String {
+    const PREFIX: &str = "${input:";
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    loop {
+        let Some(start) = rest.find(PREFIX) else {
+            result.push_str(rest);
+            break;
+        };
+        result.push_str(&rest[..start]);
+        let after_prefix = &rest[start + PREFIX.len()..];
+        let Some(end) = after_prefix.find('}') else {
+            result.push_str(&rest[start..]);
+            break;
+        };
+        let id = &after_prefix[..end];
+        match values.get(id) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..start + PREFIX.len() + end + 1]),
+        }
+        rest = &after_prefix[end + 1..];
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn referenced_input_ids_finds_a_single_reference() {
+        let ids = referenced_input_ids("echo ${input:greeting}");
+        assert_eq!(ids, vec!["greeting".to_string()]);
+    }
+
+    #[test]
+    fn referenced_input_ids_finds_repeated_occurrences_of_the_same_id() {
+        let ids = referenced_input_ids("${input:name}, ${input:name}!");
+        assert_eq!(ids, vec!["name".to_string(), "name".to_string()]);
+    }
+
+    #[test]
+    fn referenced_input_ids_stops_at_an_unterminated_reference() {
+        let ids = referenced_input_ids("echo ${input:greeting}, ${input:unterminated");
+        assert_eq!(ids, vec!["greeting".to_string()]);
+    }
+
+    #[test]
+    fn substitute_inputs_replaces_a_single_reference() {
+        let mut values = collections::HashMap::default();
+        values.insert("greeting".to_string(), "hello".to_string());
+
+        assert_eq!(
+            substitute_inputs("echo ${input:greeting}", &values),
+            "echo hello"
+        );
+    }
+
+    #[test]
+    fn substitute_inputs_replaces_every_occurrence_of_the_same_id() {
+        let mut values = collections::HashMap::default();
+        values.insert("name".to_string(), "Zed".to_string());
+
+        assert_eq!(
+            substitute_inputs("${input:name}, ${input:name}!", &values),
+            "Zed, Zed!"
+        );
+    }
+
+    #[test]
+    fn substitute_inputs_leaves_unresolved_references_untouched() {
+        let values = collections::HashMap::default();
+
+        assert_eq!(
+            substitute_inputs("echo ${input:missing}", &values),
+            "echo ${input:missing}"
+        );
+    }
+
+    #[test]
+    fn substitute_inputs_leaves_an_unterminated_reference_untouched() {
+        let mut values = collections::HashMap::default();
+        values.insert("greeting".to_string(), "hello".to_string());
+
+        assert_eq!(
+            substitute_inputs("echo ${input:greeting}, ${input:unterminated", &values),
+            "echo hello, ${input:unterminated"
+        );
+    }
+}
@@ -1,12 +1,16 @@
 //! Baseline interface of Tasks in Zed: all tasks in Zed are intended to use those for implementing their own logic.
 #![deny(missing_docs)]
 
+mod input;
 pub mod oneshot_source;
+pub mod scheduler;
 pub mod static_source;
+mod variable_provider;
 mod vscode_format;
 
-use collections::HashMap;
+use collections::{HashMap, HashSet};
 use gpui::ModelContext;
+pub use input::{InputResolver, TaskInput, TaskInputKind};
 use schemars::gen::SchemaSettings;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -14,11 +18,13 @@ use std::any::Any;
 use std::borrow::Cow;
 use std::path::PathBuf;
 use std::sync::Arc;
+pub use variable_provider::{VariableProvider, VariableProviderRegistry};
 pub use vscode_format::VsCodeTaskFile;
 
 /// Task identifier, unique within the application.
 /// Based on it, task reruns and terminal tabs are managed.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
 pub struct TaskId(pub String);
 
 /// Contains all information needed by Zed to spawn a new terminal tab for the given task.
@@ -92,6 +98,64 @@ impl std::fmt::Display for VariableName {
     }
 }
 
+/// Parses a `ZED_...` key (as it appears without the `$`/`${`/`}` wrapper) back into the
+/// [`VariableName`] it refers to, or `None` if it isn't a Zed variable at all.
+fn variable_name_from_key(key: &str) -> Option<VariableName> {
+    let suffix = key.strip_prefix("ZED_")?;
+    Some(match suffix {
+        "FILE" => VariableName::File,
+        "WORKTREE_ROOT" => VariableName::WorktreeRoot,
+        "SYMBOL" => VariableName::Symbol,
+        "ROW" => VariableName::Row,
+        "COLUMN" => VariableName::Column,
+        "SELECTED_TEXT" => VariableName::SelectedText,
+        custom => VariableName::Custom(Cow::Owned(custom.to_string())),
+    })
+}
+
+/// Scans `text` for every `$NAME`/`${NAME}` token (`subst`'s substitution syntax) and returns
+/// the raw key of each one, in order of appearance.
+fn scan_variable_keys(text: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'$' {
+            if bytes.get(i + 1) == Some(&b'{') {
+                if let Some(end) = text[i + 2..].find('}') {
+                    keys.push(&text[i + 2..i + 2 + end]);
+                    i += 2 + end + 1;
+                    continue;
+                }
+            } else {
+                let start = i + 1;
+                let mut end = start;
+                while bytes
+                    .get(end)
+                    .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_')
+                {
+                    end += 1;
+                }
+                if end > start {
+                    keys.push(&text[start..end]);
+                    i = end;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// Every `ZED_...` variable referenced in `text`, whether or not it's a recognized built-in.
+fn scan_zed_variables(text: &str) -> HashSet<VariableName> {
+    scan_variable_keys(text)
+        .into_iter()
+        .filter_map(variable_name_from_key)
+        .collect()
+}
+
 /// Container for predefined environment variables that describe state of Zed at the time the task was spawned.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct TaskVariables(HashMap<VariableName, String>);
@@ -140,9 +204,22 @@ pub trait Task {
     fn name(&self) -> &str;
     /// Task's current working directory. If `None`, current project's root will be used.
     fn cwd(&self) -> Option<&str>;
+    /// Other tasks that have to run (and exit successfully, for non-concurrent steps) before this one.
+    /// Empty for tasks that do not declare `depends_on`.
+    fn dependencies(&self) -> &[TaskId];
+    /// How `dependencies` should be scheduled relative to one another.
+    fn depends_order(&self) -> DependsOrder;
+    /// Whether multiple instances of this task are allowed to run concurrently, or whether a
+    /// new run should wait for an already-running instance of it to finish first.
+    fn allow_concurrent_runs(&self) -> bool;
+    /// Every `$ZED_...`/`${ZED_...}` variable referenced anywhere in this task, whether or not
+    /// it's currently satisfiable. Callers can use this to pre-filter which tasks to even offer.
+    fn required_variables(&self) -> HashSet<VariableName>;
     /// Sets up everything needed to spawn the task in the given directory (`cwd`).
     /// If a task is intended to be spawned in the terminal, it should return the corresponding struct filled with the data necessary.
-    fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal>;
+    /// `inputs` is used to resolve any `${input:ID}` reference in the task's fields; a task
+    /// referencing an input that cannot be resolved (undeclared, or the user cancelled) is omitted.
+    fn prepare_exec(&self, cx: TaskContext, inputs: &dyn InputResolver) -> Option<SpawnInTerminal>;
 }
 
 /// TODO kb proper docs
@@ -165,37 +242,122 @@ impl TaskTemplate {
             },
         })
     }
+
+    /// Collects every `${input:ID}` id referenced anywhere in the template.
+    fn referenced_input_ids(&self) -> HashSet<String> {
+        let mut ids = HashSet::default();
+        ids.extend(input::referenced_input_ids(&self.definition.label));
+        ids.extend(input::referenced_input_ids(&self.definition.command));
+        for arg in &self.definition.args {
+            ids.extend(input::referenced_input_ids(arg));
+        }
+        for value in self.definition.env.values() {
+            ids.extend(input::referenced_input_ids(value));
+        }
+        if let Some(cwd) = &self.definition.cwd {
+            ids.extend(input::referenced_input_ids(cwd));
+        }
+        ids
+    }
+
+    /// Resolves every `${input:ID}` reference in the template via `inputs`, returning `None`
+    /// if some reference is undeclared or the user cancelled its prompt.
+    fn resolve_inputs(&self, inputs: &dyn InputResolver) -> Option<HashMap<String, String>> {
+        self.referenced_input_ids()
+            .into_iter()
+            .map(|id| {
+                let declared = self.definition.inputs.iter().find(|input| input.id == id)?;
+                let value = inputs.resolve(declared)?;
+                Some((id, value))
+            })
+            .collect()
+    }
+
+    /// Every field of the definition that can contain variable references.
+    fn all_fields(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.definition.label.as_str())
+            .chain(std::iter::once(self.definition.command.as_str()))
+            .chain(self.definition.args.iter().map(String::as_str))
+            .chain(self.definition.env.values().map(String::as_str))
+            .chain(self.definition.cwd.as_deref())
+    }
 }
 
 impl Task for TaskTemplate {
-    fn prepare_exec(&self, cx: TaskContext) -> Option<SpawnInTerminal> {
+    fn prepare_exec(&self, cx: TaskContext, inputs: &dyn InputResolver) -> Option<SpawnInTerminal> {
         let TaskContext {
             cwd,
             task_variables,
         } = cx;
-        // TODO kb ensure all substitutions are possible to do: no `cwd` has the task prefix, no `env`, `args`, `label`, or `command` have vars with task prefix that are not in `task_variables`. Omit such tasks. + test this
-        let task_variables = task_variables.into_env_variables();
-        let cwd = self
+        let input_values = self.resolve_inputs(inputs)?;
+        let label = input::substitute_inputs(&self.definition.label, &input_values);
+        let command = input::substitute_inputs(&self.definition.command, &input_values);
+        let args = self
+            .definition
+            .args
+            .iter()
+            .map(|arg| input::substitute_inputs(arg, &input_values))
+            .collect();
+        let env: HashMap<String, String> = self
+            .definition
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), input::substitute_inputs(value, &input_values)))
+            .collect();
+        let cwd_template = self
             .definition
             .cwd
-            .clone()
+            .as_ref()
+            .map(|path| input::substitute_inputs(path, &input_values));
+        let task_variables = task_variables.into_env_variables();
+
+        // Ensure all substitutions are possible to do: no `label`, `command`, `args`, `env` or
+        // `cwd` reference a `$ZED_`/`${ZED_...}` variable that isn't in `task_variables`. Omit
+        // the task instead of spawning a command with a dangling token.
+        let fields_to_check = std::iter::once(label.as_str())
+            .chain(std::iter::once(command.as_str()))
+            .chain(args.iter().map(String::as_str))
+            .chain(env.values().map(String::as_str))
+            .chain(cwd_template.as_deref());
+        for field in fields_to_check {
+            if scan_variable_keys(field)
+                .into_iter()
+                .any(|key| key.starts_with("ZED_") && !task_variables.contains_key(key))
+            {
+                return None;
+            }
+        }
+
+        let label = subst::substitute(&label, &task_variables).ok()?;
+        let command = subst::substitute(&command, &task_variables).ok()?;
+        let args = args
+            .iter()
+            .map(|arg| subst::substitute(arg, &task_variables))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let mut definition_env: HashMap<String, String> = env
+            .iter()
+            .map(|(key, value)| {
+                subst::substitute(value, &task_variables).map(|value| (key.clone(), value))
+            })
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let cwd = cwd_template
             .and_then(|path| {
                 subst::substitute(&path, &task_variables)
                     .map(Into::into)
                     .ok()
             })
             .or(cwd);
-        let mut definition_env = self.definition.env.clone();
         definition_env.extend(task_variables);
         Some(SpawnInTerminal {
             id: self.id.clone(),
             cwd,
             use_new_terminal: self.definition.use_new_terminal,
             allow_concurrent_runs: self.definition.allow_concurrent_runs,
-            // TODO kb use expanded label here
-            label: self.definition.label.clone(),
-            command: self.definition.command.clone(),
-            args: self.definition.args.clone(),
+            label,
+            command,
+            args,
             reveal: self.definition.reveal,
             env: definition_env,
         })
@@ -212,6 +374,22 @@ impl Task for TaskTemplate {
     fn cwd(&self) -> Option<&str> {
         self.definition.cwd.as_deref()
     }
+
+    fn required_variables(&self) -> HashSet<VariableName> {
+        self.all_fields().flat_map(scan_zed_variables).collect()
+    }
+
+    fn dependencies(&self) -> &[TaskId] {
+        &self.definition.depends_on
+    }
+
+    fn depends_order(&self) -> DependsOrder {
+        self.definition.depends_order
+    }
+
+    fn allow_concurrent_runs(&self) -> bool {
+        self.definition.allow_concurrent_runs
+    }
 }
 
 /// Static task definition from the tasks config file.
@@ -242,6 +420,28 @@ pub struct Definition {
     /// * `never` — avoid changing current terminal pane focus, but still add/reuse the task's tab there
     #[serde(default)]
     pub reveal: RevealStrategy,
+    /// Other tasks (by id) that must run before this one, turning it into a compound task.
+    #[serde(default)]
+    pub depends_on: Vec<TaskId>,
+    /// How `depends_on` should be scheduled relative to one another.
+    #[serde(default)]
+    pub depends_order: DependsOrder,
+    /// User-prompted inputs, referenced elsewhere in this definition as `${input:ID}`.
+    #[serde(default)]
+    pub inputs: Vec<TaskInput>,
+}
+
+/// How a compound task's `depends_on` entries should be scheduled relative to one another,
+/// mirroring VS Code's `dependsOrder`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DependsOrder {
+    /// Launch every dependency at once.
+    #[default]
+    Parallel,
+    /// Run dependencies one after another, only starting the next one once the previous
+    /// process has exited successfully.
+    Sequence,
 }
 
 /// What to do with the terminal pane and tab, after the command was started.
@@ -284,3 +484,86 @@ pub trait TaskSource: Any {
         cx: &mut ModelContext<Box<dyn TaskSource>>,
     ) -> Vec<Arc<dyn Task>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoInputs;
+
+    impl InputResolver for NoInputs {
+        fn resolve(&self, _: &TaskInput) -> Option<String> {
+            None
+        }
+    }
+
+    fn template(command: &str, env: &[(&str, &str)]) -> TaskTemplate {
+        TaskTemplate {
+            id: TaskId("test".into()),
+            definition: Definition {
+                label: command.to_string(),
+                command: command.to_string(),
+                env: env
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+                ..Definition::default()
+            },
+        }
+    }
+
+    #[test]
+    fn substitutes_custom_variables_containing_whitespace() {
+        let task = template("echo ${ZED_CUSTOM_GREETING}", &[]);
+        let mut task_variables = TaskVariables::default();
+        task_variables.insert(
+            VariableName::Custom("CUSTOM_GREETING".into()),
+            "hello world".to_string(),
+        );
+        let cx = TaskContext {
+            cwd: None,
+            task_variables,
+        };
+
+        let spawn_in_terminal = task.prepare_exec(cx, &NoInputs).expect("should spawn");
+        assert_eq!(spawn_in_terminal.command, "echo hello world");
+    }
+
+    #[test]
+    fn omits_task_with_unsatisfiable_variable() {
+        let task = template("echo $ZED_UNDEFINED", &[]);
+        let cx = TaskContext::default();
+
+        assert!(task.prepare_exec(cx, &NoInputs).is_none());
+    }
+
+    #[test]
+    fn omits_task_with_unsatisfiable_variable_in_env() {
+        let task = template("echo ok", &[("SOME_VAR", "$ZED_UNDEFINED")]);
+        let cx = TaskContext::default();
+
+        assert!(task.prepare_exec(cx, &NoInputs).is_none());
+    }
+
+    #[test]
+    fn omits_task_referencing_an_undeclared_input() {
+        let mut task = template("echo ${input:greeting}", &[]);
+        task.definition.inputs = Vec::new();
+        let cx = TaskContext::default();
+
+        assert!(task.prepare_exec(cx, &NoInputs).is_none());
+    }
+
+    #[test]
+    fn omits_task_when_the_user_cancels_an_input_prompt() {
+        let mut task = template("echo ${input:greeting}", &[]);
+        task.definition.inputs = vec![TaskInput {
+            id: "greeting".into(),
+            description: "Greeting to echo".into(),
+            kind: TaskInputKind::PromptString { default: None },
+        }];
+        let cx = TaskContext::default();
+
+        assert!(task.prepare_exec(cx, &NoInputs).is_none());
+    }
+}
@@ -0,0 +1,391 @@
+//! Turns a task's `depends_on`/`depends_order` declarations into an [`ExecutionPlan`]:
+//! an ordered list of steps, where each step is either a single task or a batch of
+//! tasks that can be launched concurrently. Mirrors the scheduling VS Code performs
+//! for compound tasks in `tasks.json`.
+
+use std::sync::Arc;
+
+use collections::{HashMap, HashSet};
+
+use crate::{DependsOrder, Task, TaskId};
+
+/// A single step of an [`ExecutionPlan`].
+#[derive(Clone)]
+pub enum ExecutionStep {
+    /// Run one task and wait for it to exit (with a zero status, for non-terminal steps) before moving on.
+    Single(Arc<dyn Task>),
+    /// Launch every task in the batch at once; the plan only advances once all of them have exited.
+    ///
+    /// This crate only knows about the tasks reachable from one root's `depends_on` graph, not
+    /// about what else might already be running — so it cannot itself tell whether launching a
+    /// batch member concurrently would violate its [`Task::allow_concurrent_runs`]. The executor
+    /// that actually spawns a batch is responsible for checking that flag against its own
+    /// running-task registry (e.g. serializing after any in-flight instance of the same id)
+    /// before launching a member concurrently with the rest of the batch.
+    ///
+    /// TODO: no such executor exists in this crate yet — `ExecutionPlan` isn't wired into the
+    /// terminal-spawning scheduler at all, so today nothing actually reads `allow_concurrent_runs`
+    /// anywhere. Track that follow-up so this boundary doesn't quietly become "nobody's job".
+    Concurrent(Vec<Arc<dyn Task>>),
+}
+
+/// An ordered sequence of [`ExecutionStep`]s that satisfies a task's transitive
+/// `depends_on` graph, with the task itself as the final step.
+#[derive(Clone, Default)]
+pub struct ExecutionPlan(pub Vec<ExecutionStep>);
+
+/// Failure modes when turning a task's dependency declarations into an [`ExecutionPlan`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum TaskDependencyError {
+    /// `task` declares a dependency on `dependency`, but no task with that id is available to schedule.
+    MissingDependency {
+        /// The task whose `depends_on` references an unknown id.
+        task: TaskId,
+        /// The unknown id it referenced.
+        dependency: TaskId,
+    },
+    /// The dependency graph contains a cycle, which makes it impossible to schedule.
+    /// Lists the ids of the cycle, in dependency order, starting and ending with the same id.
+    Cycle(Vec<TaskId>),
+}
+
+impl std::fmt::Display for TaskDependencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MissingDependency { task, dependency } => write!(
+                f,
+                "task `{}` depends on `{}`, which is not available to schedule",
+                task.0, dependency.0
+            ),
+            Self::Cycle(cycle) => {
+                write!(
+                    f,
+                    "cyclic task dependency: {}",
+                    cycle
+                        .iter()
+                        .map(|id| id.0.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TaskDependencyError {}
+
+/// Builds an [`ExecutionPlan`] for `root`, resolving its transitive `depends_on` graph
+/// against `available` (every task that could be scheduled, keyed by id).
+pub fn plan_for(
+    root: &TaskId,
+    available: &HashMap<TaskId, Arc<dyn Task>>,
+) -> Result<ExecutionPlan, TaskDependencyError> {
+    // `successors(id)` are the ids that cannot run until `id` has finished: its dependents,
+    // plus (for `sequence`-ordered tasks) the next sibling in `depends_on`.
+    let mut successors: HashMap<TaskId, Vec<TaskId>> = HashMap::default();
+    let mut in_degree: HashMap<TaskId, usize> = HashMap::default();
+    let mut reachable = HashSet::default();
+
+    let mut frontier = vec![root.clone()];
+    while let Some(id) = frontier.pop() {
+        if !reachable.insert(id.clone()) {
+            continue;
+        }
+        in_degree.entry(id.clone()).or_insert(0);
+        let task = available
+            .get(&id)
+            .ok_or_else(|| TaskDependencyError::MissingDependency {
+                task: root.clone(),
+                dependency: id.clone(),
+            })?;
+        let deps = task.dependencies();
+        for dep in deps {
+            if !available.contains_key(dep) {
+                return Err(TaskDependencyError::MissingDependency {
+                    task: id.clone(),
+                    dependency: dep.clone(),
+                });
+            }
+            successors.entry(dep.clone()).or_default().push(id.clone());
+            *in_degree.entry(id.clone()).or_insert(0) += 1;
+            frontier.push(dep.clone());
+        }
+        if task.depends_order() == DependsOrder::Sequence {
+            for pair in deps.windows(2) {
+                let [earlier, later] = pair else { continue };
+                successors
+                    .entry(earlier.clone())
+                    .or_default()
+                    .push(later.clone());
+                *in_degree.entry(later.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Kahn's algorithm, peeling off one layer of zero-in-degree nodes at a time: every
+    // node in a layer can run concurrently, since it doesn't depend on anything left to schedule.
+    let mut remaining_in_degree = in_degree.clone();
+    let mut scheduled = Vec::with_capacity(reachable.len());
+    let mut steps = Vec::new();
+    loop {
+        let layer: Vec<TaskId> = remaining_in_degree
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(id, _)| id.clone())
+            .collect();
+        if layer.is_empty() {
+            break;
+        }
+        for id in &layer {
+            remaining_in_degree.remove(id);
+            scheduled.push(id.clone());
+        }
+        for id in &layer {
+            if let Some(next) = successors.get(id) {
+                for successor in next {
+                    if let Some(degree) = remaining_in_degree.get_mut(successor) {
+                        *degree = degree.saturating_sub(1);
+                    }
+                }
+            }
+        }
+        let mut tasks: Vec<Arc<dyn Task>> = layer.iter().map(|id| available[id].clone()).collect();
+        if tasks.len() == 1 {
+            steps.push(ExecutionStep::Single(tasks.remove(0)));
+        } else {
+            steps.push(ExecutionStep::Concurrent(tasks));
+        }
+    }
+
+    if scheduled.len() != reachable.len() {
+        let stuck = remaining_in_degree.keys().cloned().collect();
+        let cycle = find_cycle(&stuck, &successors)
+            .expect("a node stuck after Kahn's algorithm must lie on some cycle");
+        return Err(TaskDependencyError::Cycle(cycle));
+    }
+
+    Ok(ExecutionPlan(steps))
+}
+
+/// DFS-based back-edge detection: returns the first cycle found among `nodes` by walking
+/// `edges`, or `None` if `nodes`/`edges` are in fact acyclic. Unlike walking forward from an
+/// arbitrary stuck node, this is guaranteed to return a path that is an actual cycle (every
+/// node in it reachable from, and able to reach, every other node in it), since it backs off a
+/// branch the moment it dead-ends instead of stopping there.
+fn find_cycle(
+    nodes: &HashSet<TaskId>,
+    edges: &HashMap<TaskId, Vec<TaskId>>,
+) -> Option<Vec<TaskId>> {
+    fn visit(
+        node: &TaskId,
+        edges: &HashMap<TaskId, Vec<TaskId>>,
+        done: &mut HashSet<TaskId>,
+        stack: &mut Vec<TaskId>,
+    ) -> Option<Vec<TaskId>> {
+        if done.contains(node) {
+            return None;
+        }
+        if let Some(cycle_start) = stack.iter().position(|id| id == node) {
+            let mut cycle = stack[cycle_start..].to_vec();
+            cycle.push(node.clone());
+            return Some(cycle);
+        }
+        stack.push(node.clone());
+        if let Some(next_nodes) = edges.get(node) {
+            for next in next_nodes {
+                if let Some(cycle) = visit(next, edges, done, stack) {
+                    return Some(cycle);
+                }
+            }
+        }
+        stack.pop();
+        done.insert(node.clone());
+        None
+    }
+
+    let mut done = HashSet::default();
+    let mut stack = Vec::new();
+    for node in nodes {
+        if let Some(cycle) = visit(node, edges, &mut done, &mut stack) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InputResolver, SpawnInTerminal, TaskContext, VariableName};
+
+    struct FakeTask {
+        id: TaskId,
+        dependencies: Vec<TaskId>,
+        depends_order: DependsOrder,
+    }
+
+    impl FakeTask {
+        fn new(id: &str, dependencies: &[&str], depends_order: DependsOrder) -> Arc<dyn Task> {
+            Arc::new(Self {
+                id: TaskId(id.to_string()),
+                dependencies: dependencies
+                    .iter()
+                    .map(|id| TaskId(id.to_string()))
+                    .collect(),
+                depends_order,
+            })
+        }
+    }
+
+    impl Task for FakeTask {
+        fn id(&self) -> &TaskId {
+            &self.id
+        }
+
+        fn name(&self) -> &str {
+            &self.id.0
+        }
+
+        fn cwd(&self) -> Option<&str> {
+            None
+        }
+
+        fn dependencies(&self) -> &[TaskId] {
+            &self.dependencies
+        }
+
+        fn depends_order(&self) -> DependsOrder {
+            self.depends_order
+        }
+
+        fn allow_concurrent_runs(&self) -> bool {
+            false
+        }
+
+        fn required_variables(&self) -> HashSet<VariableName> {
+            HashSet::default()
+        }
+
+        fn prepare_exec(&self, _: TaskContext, _: &dyn InputResolver) -> Option<SpawnInTerminal> {
+            None
+        }
+    }
+
+    fn available(tasks: Vec<Arc<dyn Task>>) -> HashMap<TaskId, Arc<dyn Task>> {
+        tasks
+            .into_iter()
+            .map(|task| (task.id().clone(), task))
+            .collect()
+    }
+
+    fn step_ids(step: &ExecutionStep) -> Vec<TaskId> {
+        match step {
+            ExecutionStep::Single(task) => vec![task.id().clone()],
+            ExecutionStep::Concurrent(tasks) => {
+                tasks.iter().map(|task| task.id().clone()).collect()
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_dependencies_batch_into_one_concurrent_step() {
+        let available = available(vec![
+            FakeTask::new("root", &["a", "b"], DependsOrder::Parallel),
+            FakeTask::new("a", &[], DependsOrder::Parallel),
+            FakeTask::new("b", &[], DependsOrder::Parallel),
+        ]);
+
+        let plan = plan_for(&TaskId("root".into()), &available).unwrap();
+
+        assert_eq!(plan.0.len(), 2);
+        let mut first_step = step_ids(&plan.0[0]);
+        first_step.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(first_step, vec![TaskId("a".into()), TaskId("b".into())]);
+        assert_eq!(step_ids(&plan.0[1]), vec![TaskId("root".into())]);
+    }
+
+    #[test]
+    fn sequential_dependencies_run_one_at_a_time_in_order() {
+        let available = available(vec![
+            FakeTask::new("root", &["a", "b"], DependsOrder::Sequence),
+            FakeTask::new("a", &[], DependsOrder::Parallel),
+            FakeTask::new("b", &[], DependsOrder::Parallel),
+        ]);
+
+        let plan = plan_for(&TaskId("root".into()), &available).unwrap();
+
+        assert_eq!(
+            plan.0.iter().map(step_ids).collect::<Vec<_>>(),
+            vec![
+                vec![TaskId("a".into())],
+                vec![TaskId("b".into())],
+                vec![TaskId("root".into())],
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_dependency_is_reported() {
+        let available = available(vec![FakeTask::new(
+            "root",
+            &["missing"],
+            DependsOrder::Parallel,
+        )]);
+
+        let error = plan_for(&TaskId("root".into()), &available).unwrap_err();
+
+        assert_eq!(
+            error,
+            TaskDependencyError::MissingDependency {
+                task: TaskId("root".into()),
+                dependency: TaskId("missing".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn self_dependency_is_a_cycle() {
+        let available = available(vec![FakeTask::new("a", &["a"], DependsOrder::Parallel)]);
+
+        let error = plan_for(&TaskId("a".into()), &available).unwrap_err();
+
+        assert_eq!(
+            error,
+            TaskDependencyError::Cycle(vec![TaskId("a".into()), TaskId("a".into())])
+        );
+    }
+
+    #[test]
+    fn cycle_with_an_external_dependent_is_still_reported_accurately() {
+        // `d` depends on `e`, and `e -> f -> g -> e` is a genuine cycle that doesn't include
+        // `d`. The reported cycle must actually be cyclic (and mention `e`/`f`/`g`), not just
+        // the nearest stuck node (`d`) that happens to depend on it.
+        let available = available(vec![
+            FakeTask::new("d", &["e"], DependsOrder::Parallel),
+            FakeTask::new("e", &["g"], DependsOrder::Parallel),
+            FakeTask::new("f", &["e"], DependsOrder::Parallel),
+            FakeTask::new("g", &["f"], DependsOrder::Parallel),
+        ]);
+
+        let error = plan_for(&TaskId("d".into()), &available).unwrap_err();
+
+        let TaskDependencyError::Cycle(cycle) = error else {
+            panic!("expected a Cycle error, got {error:?}");
+        };
+        assert_eq!(cycle.first(), cycle.last());
+        assert!(
+            cycle.len() > 1,
+            "a real cycle must revisit a node: {cycle:?}"
+        );
+        for id in ["e", "f", "g"] {
+            assert!(
+                cycle.iter().any(|cycle_id| cycle_id.0 == id),
+                "expected `{id}` to be part of the reported cycle {cycle:?}"
+            );
+        }
+        assert!(
+            !cycle.iter().any(|cycle_id| cycle_id.0 == "d"),
+            "`d` merely depends on the cycle, it isn't part of it: {cycle:?}"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+//! Pluggable sources of [`TaskVariables`], the `${ZED_...}` counterpart to [`TaskSource`]:
+//! instead of contributing tasks, a [`VariableProvider`] contributes values a task template
+//! can reference, e.g. a Rust language server supplying `ZED_PACKAGE`, or a build server
+//! supplying `ZED_TARGET`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{TaskContext, TaskVariables};
+
+/// Supplies additional [`TaskVariables`] for a given [`TaskContext`].
+pub trait VariableProvider {
+    /// Computes the variables this provider contributes for `cx`.
+    fn provide(&self, cx: &TaskContext) -> TaskVariables;
+}
+
+/// Collects [`VariableProvider`]s and merges their output into a [`TaskContext`], the way
+/// the scheduler merges [`TaskSource`](crate::TaskSource)s' tasks into a single list.
+#[derive(Default)]
+pub struct VariableProviderRegistry {
+    providers: Vec<Arc<dyn VariableProvider>>,
+}
+
+impl VariableProviderRegistry {
+    /// Registers a new provider. Providers registered later take precedence over ones
+    /// registered earlier, should they contribute the same variable.
+    pub fn register(&mut self, provider: Arc<dyn VariableProvider>) {
+        self.providers.push(provider);
+    }
+
+    /// Builds the final [`TaskContext`] for `cwd`, merging, in increasing precedence:
+    /// `editor_variables` (the editor's own built-ins) < provider output (registration
+    /// order) < `explicit_env` (variables the user set explicitly on the task).
+    pub fn build_context(
+        &self,
+        cwd: Option<PathBuf>,
+        editor_variables: TaskVariables,
+        explicit_env: TaskVariables,
+    ) -> TaskContext {
+        let editor_cx = TaskContext {
+            cwd: cwd.clone(),
+            task_variables: editor_variables.clone(),
+        };
+        let mut task_variables = editor_variables;
+        for provider in &self.providers {
+            task_variables.extend(provider.provide(&editor_cx));
+        }
+        task_variables.extend(explicit_env);
+        TaskContext {
+            cwd,
+            task_variables,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VariableName;
+
+    struct FakeProvider {
+        name: &'static str,
+        value: &'static str,
+    }
+
+    impl VariableProvider for FakeProvider {
+        fn provide(&self, _: &TaskContext) -> TaskVariables {
+            let mut variables = TaskVariables::default();
+            variables.insert(
+                VariableName::Custom(self.name.into()),
+                self.value.to_string(),
+            );
+            variables
+        }
+    }
+
+    #[test]
+    fn providers_override_editor_builtins_but_not_explicit_env() {
+        let mut registry = VariableProviderRegistry::default();
+        registry.register(Arc::new(FakeProvider {
+            name: "PACKAGE",
+            value: "from-provider",
+        }));
+        registry.register(Arc::new(FakeProvider {
+            name: "TARGET",
+            value: "from-provider",
+        }));
+
+        let mut editor_variables = TaskVariables::default();
+        editor_variables.insert(VariableName::Custom("PACKAGE".into()), "from-editor".into());
+        editor_variables.insert(VariableName::Custom("TARGET".into()), "from-editor".into());
+        editor_variables.insert(VariableName::Custom("FILE_TYPE".into()), "rust".into());
+
+        let mut explicit_env = TaskVariables::default();
+        explicit_env.insert(VariableName::Custom("PACKAGE".into()), "from-env".into());
+
+        let cx = registry.build_context(None, editor_variables, explicit_env);
+
+        let as_env = cx.task_variables.into_env_variables();
+        assert_eq!(
+            as_env.get("ZED_PACKAGE").map(String::as_str),
+            Some("from-env")
+        );
+        assert_eq!(
+            as_env.get("ZED_TARGET").map(String::as_str),
+            Some("from-provider")
+        );
+        assert_eq!(
+            as_env.get("ZED_FILE_TYPE").map(String::as_str),
+            Some("rust")
+        );
+    }
+
+    #[test]
+    fn later_registered_providers_win() {
+        let mut registry = VariableProviderRegistry::default();
+        registry.register(Arc::new(FakeProvider {
+            name: "TARGET",
+            value: "first",
+        }));
+        registry.register(Arc::new(FakeProvider {
+            name: "TARGET",
+            value: "second",
+        }));
+
+        let cx = registry.build_context(None, TaskVariables::default(), TaskVariables::default());
+
+        let as_env = cx.task_variables.into_env_variables();
+        assert_eq!(as_env.get("ZED_TARGET").map(String::as_str), Some("second"));
+    }
+}